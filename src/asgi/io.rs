@@ -1,27 +1,47 @@
-use bytes::Buf;
+use std::io::Write;
+
+use brotli::CompressorWriter;
+use bytes::{Buf, Bytes};
+use flate2::Compression;
+use flate2::write::{DeflateEncoder, GzEncoder};
+use futures::{SinkExt, StreamExt};
+use futures::stream::{SplitSink, SplitStream};
 use hyper::{
     Body,
     Request,
     Response,
-    header::{HeaderName, HeaderValue, HeaderMap}
+    StatusCode,
+    header::{
+        HeaderName, HeaderValue, HeaderMap,
+        ACCEPT_ENCODING, CONNECTION, CONTENT_ENCODING, CONTENT_LENGTH,
+        SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, SEC_WEBSOCKET_PROTOCOL, UPGRADE
+    },
+    upgrade::{OnUpgrade, Upgraded}
 };
+use hyper::body::HttpBody;
 use pyo3::prelude::*;
 use pyo3::types::{PyBytes, PyDict};
 use std::sync::{Arc};
-use tokio::sync::{Mutex, oneshot};
+use tokio::sync::{Mutex, mpsc, oneshot};
+use tokio_tungstenite::WebSocketStream;
+use tokio_tungstenite::tungstenite::{
+    Message,
+    handshake::derive_accept_key,
+    protocol::{Role, CloseFrame, frame::coding::CloseCode}
+};
 
-use super::errors::{ASGIFlowError, UnsupportedASGIMessage};
+use super::errors::{ASGIFlowError, UnsupportedASGIMessage, WebSocketFlowError};
 use super::types::ASGIMessageType;
 
 #[pyclass(module="granian.asgi")]
 pub(crate) struct Receiver {
-    request: Arc<Mutex<Request<Body>>>
+    body: Arc<Mutex<Body>>
 }
 
 impl Receiver {
     pub fn new(request: Request<Body>) -> Self {
         Self {
-            request: Arc::new(Mutex::new(request))
+            body: Arc::new(Mutex::new(request.into_body()))
         }
     }
 }
@@ -29,39 +49,386 @@ impl Receiver {
 #[pymethods]
 impl Receiver {
     fn __call__<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
-        let req_ref = self.request.clone();
+        let body_ref = self.body.clone();
         pyo3_asyncio::tokio::future_into_py(py, async move {
-            let mut req = req_ref.lock().await;
-            let mut body = hyper::body::to_bytes(&mut *req).await.unwrap();
+            let mut body = body_ref.lock().await;
+            let frame = body.data().await;
             Ok(Python::with_gil(|py| {
-                PyBytes::new_with(py, body.len(), |bytes: &mut [u8]| {
-                    body.copy_to_slice(bytes);
-                    Ok(())
-                }).unwrap().as_ref().to_object(py)
+                let event = PyDict::new(py);
+                match frame {
+                    Some(Ok(mut chunk)) => {
+                        let bytes = PyBytes::new_with(py, chunk.len(), |buf: &mut [u8]| {
+                            chunk.copy_to_slice(buf);
+                            Ok(())
+                        }).unwrap();
+                        event.set_item("type", "http.request").unwrap();
+                        event.set_item("body", bytes).unwrap();
+                        event.set_item("more_body", !body.is_end_stream()).unwrap();
+                    },
+                    Some(Err(_)) => {
+                        event.set_item("type", "http.disconnect").unwrap();
+                    },
+                    None => {
+                        event.set_item("type", "http.request").unwrap();
+                        event.set_item("body", PyBytes::new(py, b"")).unwrap();
+                        event.set_item("more_body", false).unwrap();
+                    }
+                };
+                event.to_object(py)
             }))
         })
     }
 }
 
-#[pyclass(module="granian.asgi")]
-pub(crate) struct Sender {
+// Response content codings; server preference order comes from `CompressionConfig::algorithms`.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum ContentEncoding {
+    Identity,
+    Brotli,
+    Gzip,
+    Deflate
+}
+
+impl ContentEncoding {
+    fn token(&self) -> &'static str {
+        match self {
+            ContentEncoding::Identity => "identity",
+            ContentEncoding::Brotli => "br",
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Deflate => "deflate"
+        }
+    }
+
+    fn from_token(token: &str) -> Option<Self> {
+        match token {
+            "identity" => Some(ContentEncoding::Identity),
+            "br" => Some(ContentEncoding::Brotli),
+            "gzip" | "x-gzip" => Some(ContentEncoding::Gzip),
+            "deflate" => Some(ContentEncoding::Deflate),
+            _ => None
+        }
+    }
+}
+
+// Server-wide response compression tuning.
+#[derive(Clone)]
+pub(crate) struct CompressionConfig {
+    pub min_size: usize,
+    pub algorithms: Vec<ContentEncoding>
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self {
+            min_size: 1024,
+            algorithms: vec![
+                ContentEncoding::Brotli,
+                ContentEncoding::Gzip,
+                ContentEncoding::Deflate
+            ]
+        }
+    }
+}
+
+impl CompressionConfig {
+    // Best supported coding per RFC 7231 §5.3.4 (q-values, `identity`/`*`).
+    pub fn negotiate(&self, headers: &HeaderMap) -> ContentEncoding {
+        let header = match headers.get(ACCEPT_ENCODING).and_then(|v| v.to_str().ok()) {
+            Some(val) => val,
+            None => return ContentEncoding::Identity
+        };
+        let mut explicit: Vec<(ContentEncoding, f32)> = Vec::new();
+        let mut wildcard: Option<f32> = None;
+        for part in header.split(',') {
+            let mut it = part.split(';');
+            let token = it.next().unwrap_or("").trim().to_lowercase();
+            if token.is_empty() {
+                continue;
+            }
+            let quality = it
+                .find_map(|p| p.trim().strip_prefix("q=").map(|q| q.trim().to_owned()))
+                .and_then(|q| q.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if token == "*" {
+                wildcard = Some(quality);
+            } else if let Some(coding) = ContentEncoding::from_token(&token) {
+                explicit.push((coding, quality));
+            }
+        }
+        let quality = |coding: ContentEncoding| -> Option<f32> {
+            explicit.iter().find(|(tok, _)| *tok == coding).map(|(_, q)| *q).or(wildcard)
+        };
+        // Highest non-zero q wins; ties go to the earlier entry in `algorithms`.
+        let mut best: Option<(ContentEncoding, f32)> = None;
+        for &coding in self.algorithms.iter() {
+            match quality(coding) {
+                Some(q) if q > 0.0 && best.map_or(true, |(_, b)| q > b) => best = Some((coding, q)),
+                _ => {}
+            }
+        }
+        match (best, quality(ContentEncoding::Identity)) {
+            (Some((_, q)), Some(identity_q)) if identity_q > q => ContentEncoding::Identity,
+            (Some((coding, _)), _) => coding,
+            (None, _) => ContentEncoding::Identity
+        }
+    }
+}
+
+// Incremental compressor so each `send_body` frame is encoded as it flows out.
+enum Encoder {
+    Gzip(GzEncoder<Vec<u8>>),
+    Deflate(DeflateEncoder<Vec<u8>>),
+    Brotli(Box<CompressorWriter<Vec<u8>>>)
+}
+
+impl Encoder {
+    fn new(encoding: ContentEncoding) -> Self {
+        match encoding {
+            ContentEncoding::Gzip => {
+                Encoder::Gzip(GzEncoder::new(Vec::new(), Compression::default()))
+            },
+            ContentEncoding::Deflate => {
+                Encoder::Deflate(DeflateEncoder::new(Vec::new(), Compression::default()))
+            },
+            ContentEncoding::Brotli => {
+                Encoder::Brotli(Box::new(CompressorWriter::new(Vec::new(), 4096, 5, 22)))
+            },
+            ContentEncoding::Identity => unreachable!("identity is never encoded")
+        }
+    }
+
+    fn write(&mut self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Encoder::Gzip(enc) => {
+                let _ = enc.write_all(data);
+                let _ = enc.flush();
+                std::mem::take(enc.get_mut())
+            },
+            Encoder::Deflate(enc) => {
+                let _ = enc.write_all(data);
+                let _ = enc.flush();
+                std::mem::take(enc.get_mut())
+            },
+            Encoder::Brotli(enc) => {
+                let _ = enc.write_all(data);
+                let _ = enc.flush();
+                std::mem::take(enc.get_mut())
+            }
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        match self {
+            Encoder::Gzip(enc) => enc.finish().unwrap_or_default(),
+            Encoder::Deflate(enc) => enc.finish().unwrap_or_default(),
+            Encoder::Brotli(enc) => enc.into_inner()
+        }
+    }
+}
+
+enum BodyCommand {
+    Data(Bytes),
+    Trailers(HeaderMap)
+}
+
+// The queue is bounded so `BodyCommand::Data` sends block the calling coroutine
+// once the pump is still waiting on `send_data` capacity — real backpressure and
+// bounded memory for large downloads and SSE, rather than an unbounded backlog.
+fn spawn_body_pump() -> (mpsc::Sender<BodyCommand>, Body) {
+    let (mut body_tx, stream) = Body::channel();
+    let (cmd_tx, mut cmd_rx) = mpsc::channel::<BodyCommand>(1);
+    tokio::spawn(async move {
+        while let Some(cmd) = cmd_rx.recv().await {
+            match cmd {
+                BodyCommand::Data(chunk) => {
+                    if body_tx.send_data(chunk).await.is_err() {
+                        break;
+                    }
+                },
+                BodyCommand::Trailers(trailers) => {
+                    let _ = body_tx.send_trailers(trailers).await;
+                }
+            }
+        }
+    });
+    (cmd_tx, stream)
+}
+
+struct SenderInner {
     inited: bool,
+    started: bool,
+    data_done: bool,
     consumed: bool,
     status: i16,
     headers: HeaderMap,
-    body: Vec<u8>,
-    tx: Option<oneshot::Sender<Response<Body>>>
+    tx: Option<oneshot::Sender<Response<Body>>>,
+    body_tx: Option<mpsc::Sender<BodyCommand>>,
+    encoding: ContentEncoding,
+    min_size: usize,
+    encoder: Option<Encoder>,
+    expect_trailers: bool,
+    trailers: HeaderMap
+}
+
+impl SenderInner {
+    fn init_response(&mut self, status_code: i16, headers: HeaderMap) {
+        self.status = status_code;
+        self.headers = headers;
+        self.inited = true;
+    }
+
+    fn dispatch_response(&mut self, body: Body) {
+        if let Some(tx) = self.tx.take() {
+            let mut res = Response::new(body);
+            *res.status_mut() = hyper::StatusCode::from_u16(
+                self.status as u16
+            ).unwrap();
+            *res.headers_mut() = self.headers.to_owned();
+            let _ = tx.send(res);
+        }
+    }
+
+    fn should_compress(&self, body: &Bytes, finish: bool) -> bool {
+        if self.encoding == ContentEncoding::Identity {
+            return false;
+        }
+        if self.headers.contains_key(CONTENT_ENCODING) {
+            return false;
+        }
+        if finish && body.len() < self.min_size {
+            return false;
+        }
+        true
+    }
+
+    async fn enqueue(&self, command: BodyCommand) {
+        if let Some(body_tx) = self.body_tx.clone() {
+            let _ = body_tx.send(command).await;
+        }
+    }
+
+    async fn send_body(&mut self, body: Bytes, finish: bool) {
+        // A single non-`more_body` chunk with no trailers is sent length-framed;
+        // everything else streams through the body channel under chunked encoding.
+        if !self.started {
+            self.started = true;
+            if self.should_compress(&body, finish) {
+                self.headers.insert(
+                    CONTENT_ENCODING,
+                    HeaderValue::from_static(self.encoding.token())
+                );
+                self.headers.remove(CONTENT_LENGTH);
+                self.encoder = Some(Encoder::new(self.encoding));
+            }
+
+            if finish && !self.expect_trailers {
+                let payload = match self.encoder.take() {
+                    Some(mut encoder) => {
+                        let mut out = encoder.write(&body);
+                        out.extend_from_slice(&encoder.finish());
+                        if let Ok(len) = HeaderValue::from_str(&out.len().to_string()) {
+                            self.headers.insert(CONTENT_LENGTH, len);
+                        }
+                        Bytes::from(out)
+                    },
+                    None => {
+                        if !self.headers.contains_key(CONTENT_LENGTH) {
+                            if let Ok(len) = HeaderValue::from_str(&body.len().to_string()) {
+                                self.headers.insert(CONTENT_LENGTH, len);
+                            }
+                        }
+                        body
+                    }
+                };
+                self.dispatch_response(Body::from(payload));
+                self.consumed = true;
+                return;
+            }
+
+            let first = match self.encoder.as_mut() {
+                Some(encoder) => Bytes::from(encoder.write(&body)),
+                None => body
+            };
+            let (cmd_tx, stream) = spawn_body_pump();
+            self.body_tx = Some(cmd_tx);
+            self.dispatch_response(stream);
+            if !first.is_empty() {
+                self.enqueue(BodyCommand::Data(first)).await;
+            }
+            if finish {
+                self.finalize_body().await;
+            }
+            return;
+        }
+
+        let chunk = match self.encoder.as_mut() {
+            Some(encoder) => Bytes::from(encoder.write(&body)),
+            None => body
+        };
+        if !chunk.is_empty() {
+            self.enqueue(BodyCommand::Data(chunk)).await;
+        }
+        if finish {
+            self.finalize_body().await;
+        }
+    }
+
+    async fn finalize_body(&mut self) {
+        if let Some(encoder) = self.encoder.take() {
+            let tail = encoder.finish();
+            if !tail.is_empty() {
+                self.enqueue(BodyCommand::Data(Bytes::from(tail))).await;
+            }
+        }
+        // The data phase is closed either way; with trailers expected the
+        // channel is left open only until the trailer frame (or until the
+        // Sender drops, which ends the stream if trailers never arrive).
+        self.data_done = true;
+        if !self.expect_trailers {
+            self.body_tx = None;
+            self.consumed = true;
+        }
+    }
+
+    async fn send_trailers(&mut self, trailers: HeaderMap, finish: bool) {
+        self.trailers.extend(trailers);
+        if finish {
+            if let Some(body_tx) = self.body_tx.take() {
+                let trailers = std::mem::take(&mut self.trailers);
+                let _ = body_tx.send(BodyCommand::Trailers(trailers)).await;
+            }
+            self.consumed = true;
+        }
+    }
+}
+
+#[pyclass(module="granian.asgi")]
+pub(crate) struct Sender {
+    inner: Arc<Mutex<SenderInner>>
 }
 
 impl Sender {
-    pub fn new(tx: Option<oneshot::Sender<Response<Body>>>) -> Self {
+    pub fn new(
+        tx: Option<oneshot::Sender<Response<Body>>>,
+        encoding: ContentEncoding,
+        min_size: usize
+    ) -> Self {
         Self {
-            inited: false,
-            consumed: false,
-            status: 0,
-            headers: HeaderMap::new(),
-            body: Vec::new(),
-            tx: tx
+            inner: Arc::new(Mutex::new(SenderInner {
+                inited: false,
+                started: false,
+                data_done: false,
+                consumed: false,
+                status: 0,
+                headers: HeaderMap::new(),
+                tx: tx,
+                body_tx: None,
+                encoding: encoding,
+                min_size: min_size,
+                encoder: None,
+                expect_trailers: false,
+                trailers: HeaderMap::new()
+            }))
         }
     }
 
@@ -75,6 +442,7 @@ impl Sender {
                 match message_type {
                     "http.response.start" => Ok(ASGIMessageType::Start),
                     "http.response.body" => Ok(ASGIMessageType::Body),
+                    "http.response.trailers" => Ok(ASGIMessageType::Trailers),
                     _ => Err(UnsupportedASGIMessage)
                 }
             },
@@ -132,55 +500,469 @@ impl Sender {
         (body, more)
     }
 
-    fn init_response(&mut self, status_code: i16, headers: HeaderMap) {
-        self.status = status_code;
-        self.headers = headers;
-        self.inited = true;
+    fn adapt_flag(&self, message: &PyDict, key: &str) -> bool {
+        message
+            .get_item(key)
+            .and_then(|item| item.extract().ok())
+            .unwrap_or(false)
     }
+}
 
-    fn send_body(&mut self, body: &[u8], finish: bool) {
-        self.body.extend_from_slice(body);
-        if finish {
-            if let Some(tx) = self.tx.take() {
-                let mut res = Response::new(self.body.to_owned().into());
-                *res.status_mut() = hyper::StatusCode::from_u16(
-                    self.status as u16
-                ).unwrap();
-                *res.headers_mut() = self.headers.to_owned();
-                let _ = tx.send(res);
-            }
-            self.consumed = true
+#[pymethods]
+impl Sender {
+    fn __call__<'p>(&self, py: Python<'p>, data: &PyDict) -> PyResult<&'p PyAny> {
+        // Everything we need is read off the GIL-bound dict up front; the send
+        // then awaits so a full body channel suspends the Python coroutine.
+        let message_type = self.adapt_message_type(data)?;
+        let status = self.adapt_status_code(data).unwrap_or(0);
+        let headers = self.adapt_headers(data);
+        let trailers_flag = self.adapt_flag(data, "trailers");
+        let (body, more_body) = self.adapt_body(data);
+        let more_trailers = self.adapt_flag(data, "more_trailers");
+
+        let inner_ref = self.inner.clone();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let mut inner = inner_ref.lock().await;
+            match message_type {
+                ASGIMessageType::Start => {
+                    if inner.inited {
+                        return Err(ASGIFlowError.into());
+                    }
+                    inner.expect_trailers = trailers_flag;
+                    inner.init_response(status, headers);
+                },
+                ASGIMessageType::Body => {
+                    match (inner.inited, inner.data_done, inner.consumed) {
+                        (true, false, false) => {
+                            inner.send_body(Bytes::from(body), !more_body).await;
+                        },
+                        _ => return Err(ASGIFlowError.into())
+                    }
+                },
+                ASGIMessageType::Trailers => {
+                    match (inner.started, inner.consumed, inner.expect_trailers) {
+                        (true, false, true) => {
+                            inner.send_trailers(headers, !more_trailers).await;
+                        },
+                        _ => return Err(ASGIFlowError.into())
+                    }
+                },
+                _ => return Err(UnsupportedASGIMessage.into())
+            };
+            Ok(Python::with_gil(|py| py.None()))
+        })
+    }
+}
+type WsSink = SplitSink<WebSocketStream<Upgraded>, Message>;
+type WsSource = SplitStream<WebSocketStream<Upgraded>>;
+
+// Handshake state consumed once on `websocket.accept`.
+struct Handshake {
+    accept_key: Option<String>,
+    subprotocols: Vec<String>,
+    upgrade: Option<OnUpgrade>,
+    split: bool
+}
+
+// Read and write halves sit behind separate mutexes so a blocked receive never
+// holds up a concurrent server-initiated send.
+struct WebSocketState {
+    handshake: Mutex<Handshake>,
+    reader: Mutex<Option<WsSource>>,
+    writer: Mutex<Option<WsSink>>
+}
+
+impl WebSocketState {
+    // Drive the deferred upgrade exactly once and split the resulting stream into
+    // its read/write halves. Only the brief setup holds the handshake lock;
+    // steady-state reads and writes contend on their own mutexes.
+    async fn ensure_split(&self) -> Result<(), WebSocketFlowError> {
+        let mut handshake = self.handshake.lock().await;
+        if !handshake.split {
+            let upgrade = handshake.upgrade.take().ok_or(WebSocketFlowError)?;
+            let upgraded = upgrade.await.map_err(|_| WebSocketFlowError)?;
+            let ws = WebSocketStream::from_raw_socket(upgraded, Role::Server, None).await;
+            let (sink, source) = ws.split();
+            *self.reader.lock().await = Some(source);
+            *self.writer.lock().await = Some(sink);
+            handshake.split = true;
+        }
+        Ok(())
+    }
+}
+
+#[pyclass(module="granian.asgi")]
+pub(crate) struct WebSocketReceiver {
+    state: Arc<WebSocketState>,
+    connected: Arc<Mutex<bool>>
+}
+
+impl WebSocketReceiver {
+    pub fn new(state: Arc<WebSocketState>) -> Self {
+        Self {
+            state: state,
+            connected: Arc::new(Mutex::new(false))
         }
     }
 }
 
 #[pymethods]
-impl Sender {
-    fn __call__<'p>(&mut self, data: &PyDict) -> PyResult<()> {
-        match self.adapt_message_type(data) {
-            Ok(ASGIMessageType::Start) => {
-                match self.inited {
-                    false => {
-                        self.init_response(
-                            self.adapt_status_code(data).unwrap(),
-                            self.adapt_headers(data)
-                        );
-                        Ok(())
-                    },
-                    _ => Err(ASGIFlowError.into())
+impl WebSocketReceiver {
+    fn __call__<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let state_ref = self.state.clone();
+        let connected_ref = self.connected.clone();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            let mut connected = connected_ref.lock().await;
+            if !*connected {
+                *connected = true;
+                return Ok(Python::with_gil(|py| {
+                    let event = PyDict::new(py);
+                    event.set_item("type", "websocket.connect").unwrap();
+                    event.to_object(py)
+                }));
+            }
+            drop(connected);
+
+            state_ref.ensure_split().await?;
+            let mut reader = state_ref.reader.lock().await;
+            let ws = reader.as_mut().ok_or(WebSocketFlowError)?;
+            let frame = loop {
+                match ws.next().await {
+                    Some(Ok(Message::Ping(_))) | Some(Ok(Message::Pong(_))) => continue,
+                    other => break other
                 }
-            },
-            Ok(ASGIMessageType::Body) => {
-                match (self.inited, self.consumed) {
-                    (true, false) => {
-                        let body_data = self.adapt_body(data);
-                        self.send_body(&body_data.0[..], !body_data.1);
-                        Ok(())
+            };
+            Ok(Python::with_gil(|py| {
+                let event = PyDict::new(py);
+                match frame {
+                    Some(Ok(Message::Text(data))) => {
+                        event.set_item("type", "websocket.receive").unwrap();
+                        event.set_item("text", data).unwrap();
+                    },
+                    Some(Ok(Message::Binary(data))) => {
+                        let bytes = PyBytes::new(py, &data[..]);
+                        event.set_item("type", "websocket.receive").unwrap();
+                        event.set_item("bytes", bytes).unwrap();
+                    },
+                    Some(Ok(Message::Close(frame))) => {
+                        let code = frame.map(|f| u16::from(f.code)).unwrap_or(1005);
+                        event.set_item("type", "websocket.disconnect").unwrap();
+                        event.set_item("code", code).unwrap();
                     },
-                    _ => Err(ASGIFlowError.into())
+                    _ => {
+                        event.set_item("type", "websocket.disconnect").unwrap();
+                        event.set_item("code", 1006u16).unwrap();
+                    }
+                };
+                event.to_object(py)
+            }))
+        })
+    }
+}
+
+#[pyclass(module="granian.asgi")]
+pub(crate) struct WebSocketSender {
+    accepted: Arc<Mutex<bool>>,
+    state: Arc<WebSocketState>,
+    tx: Arc<Mutex<Option<oneshot::Sender<Response<Body>>>>>
+}
+
+impl WebSocketSender {
+    pub fn new(
+        state: Arc<WebSocketState>,
+        tx: oneshot::Sender<Response<Body>>
+    ) -> Self {
+        Self {
+            accepted: Arc::new(Mutex::new(false)),
+            state: state,
+            tx: Arc::new(Mutex::new(Some(tx)))
+        }
+    }
+
+    fn adapt_message_type(
+        message: &PyDict
+    ) -> Result<ASGIMessageType, UnsupportedASGIMessage> {
+        match message.get_item("type") {
+            Some(item) => {
+                let message_type: &str = item.extract()?;
+                match message_type {
+                    "websocket.accept" => Ok(ASGIMessageType::WSAccept),
+                    "websocket.send" => Ok(ASGIMessageType::WSSend),
+                    "websocket.close" => Ok(ASGIMessageType::WSClose),
+                    _ => Err(UnsupportedASGIMessage)
                 }
             },
-            Err(err) => Err(err.into())
+            _ => Err(UnsupportedASGIMessage)
+        }
+    }
+}
+
+#[pymethods]
+impl WebSocketSender {
+    fn __call__<'p>(&self, py: Python<'p>, data: &PyDict) -> PyResult<&'p PyAny> {
+        let message_type = WebSocketSender::adapt_message_type(data)?;
+        let subprotocol: Option<String> = data
+            .get_item("subprotocol")
+            .and_then(|item| item.extract().ok());
+        let extra_headers = match message_type {
+            ASGIMessageType::WSAccept => adapt_ws_headers(data),
+            _ => HeaderMap::new()
+        };
+        let text: Option<String> = data
+            .get_item("text")
+            .and_then(|item| item.extract().ok());
+        let bytes: Option<Vec<u8>> = data
+            .get_item("bytes")
+            .and_then(|item| item.extract().ok());
+        let code: u16 = data
+            .get_item("code")
+            .and_then(|item| item.extract().ok())
+            .unwrap_or(1000);
+
+        let accepted_ref = self.accepted.clone();
+        let state_ref = self.state.clone();
+        let tx_ref = self.tx.clone();
+        pyo3_asyncio::tokio::future_into_py(py, async move {
+            match message_type {
+                ASGIMessageType::WSAccept => {
+                    let mut accepted = accepted_ref.lock().await;
+                    if *accepted {
+                        return Err(WebSocketFlowError.into());
+                    }
+                    let mut handshake = state_ref.handshake.lock().await;
+                    let accept_key = handshake.accept_key.take().ok_or(WebSocketFlowError)?;
+                    let subprotocol = match subprotocol {
+                        Some(proto) if handshake.subprotocols.contains(&proto) => Some(proto),
+                        _ => None
+                    };
+                    let tx = tx_ref.lock().await.take().ok_or(WebSocketFlowError)?;
+                    let mut res = Response::new(Body::empty());
+                    *res.status_mut() = StatusCode::SWITCHING_PROTOCOLS;
+                    let headers = res.headers_mut();
+                    headers.insert(CONNECTION, HeaderValue::from_static("upgrade"));
+                    headers.insert(UPGRADE, HeaderValue::from_static("websocket"));
+                    if let Ok(val) = HeaderValue::from_str(&accept_key) {
+                        headers.insert(SEC_WEBSOCKET_ACCEPT, val);
+                    }
+                    if let Some(proto) = subprotocol {
+                        if let Ok(val) = HeaderValue::from_str(&proto) {
+                            headers.insert(SEC_WEBSOCKET_PROTOCOL, val);
+                        }
+                    }
+                    for (key, val) in extra_headers.iter() {
+                        headers.insert(key, val.to_owned());
+                    }
+                    tx.send(res).map_err(|_| WebSocketFlowError)?;
+                    *accepted = true;
+                    Ok(())
+                },
+                ASGIMessageType::WSSend => {
+                    state_ref.ensure_split().await?;
+                    let mut writer = state_ref.writer.lock().await;
+                    let ws = writer.as_mut().ok_or(WebSocketFlowError)?;
+                    let message = match (text, bytes) {
+                        (Some(data), _) => Message::Text(data),
+                        (_, Some(data)) => Message::Binary(data),
+                        _ => return Err(WebSocketFlowError.into())
+                    };
+                    ws.send(message).await.map_err(|_| WebSocketFlowError)?;
+                    Ok(())
+                },
+                ASGIMessageType::WSClose => {
+                    state_ref.ensure_split().await?;
+                    let mut writer = state_ref.writer.lock().await;
+                    let ws = writer.as_mut().ok_or(WebSocketFlowError)?;
+                    let frame = CloseFrame {
+                        code: CloseCode::from(code),
+                        reason: "".into()
+                    };
+                    let _ = ws.send(Message::Close(Some(frame))).await;
+                    let _ = ws.close().await;
+                    Ok(())
+                },
+                _ => Err(UnsupportedASGIMessage.into())
+            }?;
+            Ok(Python::with_gil(|py| py.None()))
+        })
+    }
+}
+
+fn adapt_ws_headers(message: &PyDict) -> HeaderMap {
+    let mut ret = HeaderMap::new();
+    if let Some(item) = message.get_item("headers") {
+        let accum: Vec<Vec<&[u8]>> = item.extract().unwrap_or(Vec::new());
+        for tup in accum.iter() {
+            if let (Ok(key), Ok(val)) = (
+                HeaderName::from_bytes(tup[0]),
+                HeaderValue::from_bytes(tup[1])
+            ) {
+                ret.insert(key, val);
+            }
+        }
+    }
+    ret
+}
+
+// Build the shared WebSocket transport from the upgrade request.
+pub(crate) fn websocket(
+    request: &mut Request<Body>,
+    tx: oneshot::Sender<Response<Body>>
+) -> (WebSocketReceiver, WebSocketSender) {
+    let accept_key = request
+        .headers()
+        .get(SEC_WEBSOCKET_KEY)
+        .map(|key| derive_accept_key(key.as_bytes()));
+    let subprotocols = request
+        .headers()
+        .get(SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|val| val.to_str().ok())
+        .map(|val| val.split(',').map(|p| p.trim().to_owned()).collect())
+        .unwrap_or_default();
+    let state = Arc::new(WebSocketState {
+        handshake: Mutex::new(Handshake {
+            accept_key: accept_key,
+            subprotocols: subprotocols,
+            upgrade: Some(hyper::upgrade::on(request)),
+            split: false
+        }),
+        reader: Mutex::new(None),
+        writer: Mutex::new(None)
+    });
+    (
+        WebSocketReceiver::new(state.clone()),
+        WebSocketSender::new(state, tx)
+    )
+}
+
+// --- TLS / HTTPS termination -------------------------------------------------
+
+use std::future::Future;
+use std::io::{BufReader, Error as IoError, ErrorKind};
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use rustls::{Certificate, PrivateKey, ServerConfig, ServerConnection};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::server::TlsStream;
+
+// ALPN advertises `h2` then `http/1.1` so HTTP/2 is offered with a plain fallback.
+pub(crate) fn tls_server_config(
+    cert_chain: &[u8],
+    private_key: &[u8]
+) -> Result<ServerConfig, IoError> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(cert_chain))?
+        .into_iter()
+        .map(Certificate)
+        .collect::<Vec<_>>();
+    if certs.is_empty() {
+        return Err(IoError::new(ErrorKind::InvalidInput, "no certificates found"));
+    }
+
+    let mut reader = BufReader::new(private_key);
+    let key = rustls_pemfile::read_all(&mut reader)?
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::ECKey(key) => Some(PrivateKey(key)),
+            _ => None
+        })
+        .ok_or_else(|| IoError::new(ErrorKind::InvalidInput, "no private key found"))?;
+
+    let mut config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|err| IoError::new(ErrorKind::InvalidInput, err))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(config)
+}
+
+// Negotiated transport details surfaced to the ASGI scope.
+#[derive(Clone)]
+pub(crate) struct TlsInfo {
+    pub version: Option<&'static str>,
+    pub alpn_protocol: Option<Vec<u8>>
+}
+
+impl TlsInfo {
+    pub fn from_connection(conn: &ServerConnection) -> Self {
+        Self {
+            version: conn.protocol_version().map(|v| match v {
+                rustls::ProtocolVersion::TLSv1_3 => "TLSv1.3",
+                rustls::ProtocolVersion::TLSv1_2 => "TLSv1.2",
+                _ => "TLS"
+            }),
+            alpn_protocol: conn.alpn_protocol().map(|p| p.to_vec())
         }
     }
-}
\ No newline at end of file
+
+    pub fn from_stream(stream: &TlsStream<tokio::net::TcpStream>) -> Self {
+        Self::from_connection(stream.get_ref().1)
+    }
+}
+
+// ASGI `scheme` for a connection: the secure variants when TLS is terminated.
+pub(crate) fn connection_scheme(tls: bool, websocket: bool) -> &'static str {
+    match (tls, websocket) {
+        (true, true) => "wss",
+        (true, false) => "https",
+        (false, true) => "ws",
+        (false, false) => "http"
+    }
+}
+
+// Transport details threaded into each request's extensions; the scope builder
+// reads these to set `scheme` and the TLS `extensions.tls` entry.
+#[derive(Clone)]
+pub(crate) struct ConnScope {
+    pub scheme: &'static str,
+    pub tls: Option<TlsInfo>
+}
+
+// Accept TLS connections on `listener`, terminate each with a config built by
+// `tls_server_config`, and serve it over hyper. The negotiated `TlsInfo` and
+// `scheme` ride in the request extensions so the ASGI scope can surface them.
+pub(crate) async fn serve_tls<H, F>(
+    listener: TcpListener,
+    config: ServerConfig,
+    websocket: bool,
+    handler: H
+) -> Result<(), IoError>
+where
+    H: Fn(Request<Body>) -> F + Clone + Send + Sync + 'static,
+    F: Future<Output = Response<Body>> + Send + 'static
+{
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+    loop {
+        let (tcp, _peer) = listener.accept().await?;
+        let acceptor = acceptor.clone();
+        let handler = handler.clone();
+        tokio::spawn(async move {
+            let stream = match acceptor.accept(tcp).await {
+                Ok(stream) => stream,
+                Err(_) => return
+            };
+            let scope = ConnScope {
+                scheme: connection_scheme(true, websocket),
+                tls: Some(TlsInfo::from_stream(&stream))
+            };
+            let service = service_fn(move |mut req: Request<Body>| {
+                req.extensions_mut().insert(scope.clone());
+                let handler = handler.clone();
+                async move { Ok::<_, IoError>(handler(req).await) }
+            });
+            let _ = Http::new()
+                .serve_connection(stream, service)
+                .with_upgrades()
+                .await;
+        });
+    }
+}
+
+// Advertise the response extensions Granian implements — the trailers extension.
+pub(crate) fn response_extensions(py: Python) -> PyResult<&PyDict> {
+    let extensions = PyDict::new(py);
+    extensions.set_item("http.response.trailers", PyDict::new(py))?;
+    Ok(extensions)
+}